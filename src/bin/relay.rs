@@ -0,0 +1,191 @@
+//! Relay server for networked telephone pictionary.
+//!
+//! Holds a single room of N connected clients in a fixed turn order. N chains
+//! run concurrently — one rooted at each player. When a client finishes a turn
+//! it sends the newest `PictionaryEntry`; the relay appends it to that client's
+//! current chain and forwards it to the next player in order. It withholds the
+//! assembled stack until every player has taken every turn, then broadcasts the
+//! chains concatenated contiguously (not interleaved) so `GameReview` renders
+//! each chain grouped together.
+
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::sync::Arc;
+
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, Mutex};
+use tokio_tungstenite::tungstenite::Message;
+
+/// Mirrors the client's `Entry`; the relay never inspects the payload, it only
+/// shuttles bytes, so the variants carry opaque data.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum Entry {
+    Phrase(String),
+    Drawing(Vec<u8>),
+    Video(Vec<u8>),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct PictionaryEntry {
+    author: String,
+    entry: Entry,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum RoomMessage {
+    Join { room: String, player: usize },
+    Pass(PictionaryEntry),
+    Reveal(Vec<PictionaryEntry>),
+}
+
+/// Shared room state: one outbound channel per connected player (keyed by the
+/// seat each client claimed), the per-chain entries (indexed by the chain's
+/// originating player), the round currently in progress, the set of players who
+/// have already submitted in that round, and the room size.
+struct Room {
+    players: HashMap<usize, mpsc::UnboundedSender<Message>>,
+    chains: Vec<Vec<PictionaryEntry>>,
+    round: usize,
+    submitted: HashSet<usize>,
+    size: usize,
+}
+
+impl Room {
+    fn new(size: usize) -> Self {
+        Room {
+            players: HashMap::new(),
+            chains: vec![Vec::new(); size],
+            round: 0,
+            submitted: HashSet::new(),
+            size,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let addr = env::args().nth(1).unwrap_or_else(|| "127.0.0.1:9000".into());
+    let size: usize = env::args()
+        .nth(2)
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(2);
+
+    let listener = TcpListener::bind(&addr).await.expect("failed to bind");
+    println!("relay listening on {addr} for a room of {size}");
+
+    let room = Arc::new(Mutex::new(Room::new(size)));
+
+    while let Ok((stream, _)) = listener.accept().await {
+        let room = Arc::clone(&room);
+        tokio::spawn(handle(stream, room));
+    }
+}
+
+async fn handle(stream: TcpStream, room: Arc<Mutex<Room>>) {
+    let socket = match tokio_tungstenite::accept_async(stream).await {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+    let (mut write, mut read) = socket.split();
+
+    // The first message must be a `Join` announcing the seat this client
+    // claims; the relay honours it instead of inferring order from connects.
+    let player = loop {
+        match read.next().await {
+            Some(Ok(Message::Binary(b))) => {
+                if let Ok(RoomMessage::Join { player, .. }) =
+                    bincode::deserialize::<RoomMessage>(&b)
+                {
+                    break player;
+                }
+            }
+            Some(Ok(_)) => continue,
+            _ => return,
+        }
+    };
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+    room.lock().await.players.insert(player, tx);
+
+    // Deliver queued messages (passes and the final reveal) to this client.
+    let writer = tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            if write.send(msg).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    while let Some(Ok(msg)) = read.next().await {
+        let buf = match msg {
+            Message::Binary(b) => b,
+            Message::Text(t) => t.into_bytes(),
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        if let Ok(RoomMessage::Pass(pe)) = bincode::deserialize::<RoomMessage>(&buf) {
+            forward(&room, player, pe).await;
+        }
+    }
+
+    room.lock().await.players.remove(&player);
+    writer.abort();
+}
+
+/// Record `pe` on the chain `player` is holding this round. The round advances
+/// under a barrier: nothing is forwarded until every seat has connected *and*
+/// submitted the current round. This keeps play in lockstep so no client is
+/// handed a prompt before it has rooted its own chain, and prevents a raced or
+/// staggered pass from deadlocking the reveal.
+async fn forward(room: &Arc<Mutex<Room>>, player: usize, pe: PictionaryEntry) {
+    let mut room = room.lock().await;
+    let size = room.size;
+
+    // Ignore passes from out-of-range seats, after the game is over, or a second
+    // pass from a player who has already submitted this round.
+    if player >= size || room.round >= size || room.submitted.contains(&player) {
+        return;
+    }
+
+    // In round `r`, player `player` holds the chain rooted `r` players earlier
+    // in the ring.
+    let r = room.round;
+    let chain = (player as isize - r as isize).rem_euclid(size as isize) as usize;
+    room.chains[chain].push(pe);
+    room.submitted.insert(player);
+
+    // Barrier: hold until every seat has connected and submitted this round.
+    if room.players.len() < size || room.submitted.len() < size {
+        return;
+    }
+
+    // Round complete. The game ends once every chain holds one entry per player.
+    if r + 1 >= size {
+        let stack: Vec<PictionaryEntry> = room.chains.iter().flatten().cloned().collect();
+        let reveal = RoomMessage::Reveal(stack);
+        if let Ok(buf) = bincode::serialize(&reveal) {
+            for sink in room.players.values() {
+                let _ = sink.send(Message::Binary(buf.clone()));
+            }
+        }
+        room.round = r + 1;
+        return;
+    }
+
+    // Otherwise hand each chain to its next holder for the following round.
+    let entries: Vec<Option<PictionaryEntry>> =
+        room.chains.iter().map(|c| c.last().cloned()).collect();
+    for (chain, entry) in entries.into_iter().enumerate() {
+        let recipient = (chain + r + 1) % size;
+        if let (Some(entry), Some(sink)) = (entry, room.players.get(&recipient)) {
+            let pass = RoomMessage::Pass(entry);
+            if let Ok(buf) = bincode::serialize(&pass) {
+                let _ = sink.send(Message::Binary(buf));
+            }
+        }
+    }
+    room.round = r + 1;
+    room.submitted.clear();
+}
@@ -1,16 +1,21 @@
 #![allow(non_snake_case)]
 
 use dioxus::prelude::*;
-use dioxus::desktop::{use_asset_handler, wry::http::Response};
+use dioxus::desktop::{use_asset_handler, wry::http::{Response, StatusCode}};
 use tracing::Level;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write;
+use std::rc::Rc;
+
+mod net;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Entry {
     Phrase(String),
     Drawing(Vec<u8>),
+    Video(Vec<u8>),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -19,6 +24,18 @@ pub struct PictionaryEntry {
     pub entry: Entry,
 }
 
+/// Networking UI state shared down the tree via context. Signals are `Copy`, so
+/// the whole struct is cheap to clone into each consumer.
+#[derive(Clone, Copy)]
+struct NetCtx {
+    /// The live relay connection, once joined; `None` for a local game.
+    conn: Signal<Option<Rc<net::RequestContext>>>,
+    /// True while this player's input is locked waiting for the next entry.
+    waiting: Signal<bool>,
+    /// True once this player has submitted their own first turn.
+    rooted: Signal<bool>,
+}
+
 #[derive(Clone, Routable, Debug, PartialEq)]
 enum Route {
     #[route("/")]
@@ -61,15 +78,96 @@ fn Home() -> Element {
     let state = use_signal(|| Vec::<Entry>::new());
     let view_all = use_signal(|| false);
 
-    use_asset_handler("entry", move |request, response| {
-        match request.uri().path().strip_prefix("/entry/") {
-            Some(s) => {
-                match state()[s.parse::<usize>().unwrap()].clone() {
-                    Entry::Drawing(v) => response.respond(Response::new(v)),
-                    Entry::Phrase(_s) => return,
+    // Join a relay room if one is configured in the environment. The worker
+    // spawned by `join_room` appends received entries to `state` directly; the
+    // returned context lets this player pass their own turns on.
+    let conn = use_signal(|| None::<Rc<net::RequestContext>>);
+    // Locks this player's input after they pass; cleared by the worker when the
+    // next entry arrives (see `join_room`).
+    let waiting = use_signal(|| false);
+    // Set once this player has submitted their own first (root) turn. Until then
+    // an inbound entry must not become their prompt.
+    let rooted = use_signal(|| false);
+    use_future(move || {
+        let mut conn = conn;
+        async move {
+            if let (Ok(url), Ok(room), Ok(idx)) = (
+                std::env::var("TPI_RELAY"),
+                std::env::var("TPI_ROOM"),
+                std::env::var("TPI_PLAYER"),
+            ) {
+                if let Ok(player) = idx.parse::<usize>() {
+                    let ctx = net::join_room(&url, room, player, state, waiting).await;
+                    *conn.write() = Some(Rc::new(ctx));
                 }
+            }
+        }
+    });
+    use_context_provider(|| NetCtx { conn, waiting, rooted });
+
+    // Cache of downscaled previews, keyed by entry index. Each preview is
+    // tagged with a hash of the source bytes so that New/Load or a networked
+    // `Reveal` replacing `state` regenerates it instead of serving the previous
+    // game's image at the same index. Populated off the UI thread.
+    let thumbs = use_signal(HashMap::<usize, (u64, Vec<u8>)>::new);
+    use_effect(move || {
+        let entries = state();
+        for (index, e) in entries.iter().enumerate() {
+            if let Entry::Drawing(v) = e {
+                let hash = hash_bytes(v);
+                if thumbs.peek().get(&index).map(|(h, _)| *h) != Some(hash) {
+                    let bytes = v.clone();
+                    let mut thumbs = thumbs;
+                    spawn(async move {
+                        // Decode/resize is CPU-bound: keep it off the UI thread.
+                        let thumb = tokio::task::spawn_blocking(move || make_thumbnail(&bytes))
+                            .await
+                            .ok()
+                            .flatten();
+                        if let Some(thumb) = thumb {
+                            thumbs.write().insert(index, (hash, thumb));
+                        }
+                    });
+                }
+            }
+        }
+    });
+
+    use_asset_handler("entry", move |request, response| {
+        let index = match request.uri().path().strip_prefix("/entry/") {
+            Some(s) => match s.parse::<usize>() {
+                Ok(i) => i,
+                Err(_) => return,
             },
             None => return,
+        };
+        // `?thumb=1` serves the cached preview when one is ready; otherwise the
+        // request falls through to the full asset.
+        let want_thumb = request
+            .uri()
+            .query()
+            .map(|q| q.contains("thumb=1"))
+            .unwrap_or(false);
+        if want_thumb {
+            if let Some((hash, thumb)) = thumbs().get(&index).cloned() {
+                // Only serve a preview made from the entry currently at `index`.
+                if matches!(&state()[index], Entry::Drawing(v) if hash_bytes(v) == hash) {
+                    response.respond(Response::new(thumb));
+                    return;
+                }
+            }
+        }
+        // Drawings and videos are served as bytes; phrases have no asset.
+        let bytes = match state()[index].clone() {
+            Entry::Drawing(v) | Entry::Video(v) => v,
+            Entry::Phrase(_s) => return,
+        };
+
+        // A wry-embedded <video> issues Range requests; honour them so it can
+        // seek and stream. Everything else gets the full body.
+        match request.headers().get("Range").and_then(|r| r.to_str().ok()) {
+            Some(range) => response.respond(range_response(&bytes, range)),
+            None => response.respond(Response::new(bytes)),
         }
     });
 
@@ -85,13 +183,62 @@ fn Home() -> Element {
     }
 }
 
+/// Build a partial-content response for a `Range: bytes=START-END` request
+/// against `bytes`. Honours the open-ended `bytes=START-` form (END defaults to
+/// `TOTAL-1`) and the suffix `bytes=-N` form (the last N bytes), and replies 416
+/// for a range that falls outside the asset.
+fn range_response(bytes: &[u8], range: &str) -> Response<Vec<u8>> {
+    let total = bytes.len();
+    let parsed = range
+        .strip_prefix("bytes=")
+        .and_then(|spec| spec.split_once('-'))
+        .and_then(|(start, end)| {
+            if start.is_empty() {
+                // Suffix form `bytes=-N`: the last N bytes of the asset.
+                let n: usize = end.parse().ok()?;
+                if n == 0 {
+                    return None;
+                }
+                return Some((total.saturating_sub(n), total.checked_sub(1)?));
+            }
+            let start: usize = start.parse().ok()?;
+            let end: usize = if end.is_empty() {
+                total.checked_sub(1)?
+            } else {
+                end.parse().ok()?
+            };
+            Some((start, end))
+        });
+
+    let (start, end) = match parsed {
+        Some((start, end)) if start <= end && end < total => (start, end),
+        _ => {
+            return Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header("Content-Range", format!("bytes */{total}"))
+                .body(Vec::new())
+                .unwrap();
+        }
+    };
+
+    let slice = bytes[start..=end].to_vec();
+    Response::builder()
+        .status(StatusCode::PARTIAL_CONTENT)
+        .header("Content-Range", format!("bytes {start}-{end}/{total}"))
+        .header("Accept-Ranges", "bytes")
+        .header("Content-Length", slice.len().to_string())
+        .body(slice)
+        .unwrap()
+}
+
 #[component]
 fn GameReview(state: Signal<Vec<Entry>>) -> Element {
-    rsx! { 
+    rsx! {
         {state().iter().enumerate().map(|(index, e)| {
             match e {
                 Entry::Phrase(phrase) => rsx! { PhraseDisplay { phrase } },
-                Entry::Drawing(_v) => rsx! { DrawingDisplay { index } },
+                Entry::Drawing(_v) => rsx! { DrawingDisplay { index, thumb: true } },
+                Entry::Video(_v) => rsx! { VideoDisplay { index } },
             }
         })}
     }
@@ -127,20 +274,78 @@ fn PhraseDisplay(phrase: String) -> Element {
     }
 }
 
+/// Cheap content fingerprint used to tag a cached thumbnail so it is
+/// regenerated when the entry at an index is replaced by a new game.
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Decode `bytes`, downscale so the longest side is at most 512 px, and
+/// re-encode as PNG for the review grid. Returns `None` if the bytes are not a
+/// decodable image.
+fn make_thumbnail(bytes: &[u8]) -> Option<Vec<u8>> {
+    let img = image::load_from_memory(bytes).ok()?;
+    let thumb = img.thumbnail(512, 512);
+    let mut out = std::io::Cursor::new(Vec::new());
+    thumb.write_to(&mut out, image::ImageFormat::Png).ok()?;
+    Some(out.into_inner())
+}
+
 #[component]
-fn DrawingDisplay(index: usize) -> Element {
+fn DrawingDisplay(index: usize, #[props(default)] thumb: bool) -> Element {
+    // The review grid asks for the lightweight preview; an individually viewed
+    // entry asks for the full-resolution asset.
+    let src = if thumb {
+        format!("entry/{index}?thumb=1")
+    } else {
+        format!("entry/{index}")
+    };
     rsx! {
         img {
             width:"600px",
             height:"600px",
             "object-fit": "contain",
+            src: "{src}"
+        }
+    }
+}
+
+#[component]
+fn VideoDisplay(index: usize) -> Element {
+    rsx! {
+        video {
+            width: "600px",
+            height: "600px",
+            "object-fit": "contain",
+            controls: true,
             src: "entry/{index}"
         }
-    }    
+    }
 }
 
 #[component]
 fn EntryDisplay(state: Signal<Vec<Entry>>) -> Element {
+    let net = use_context::<NetCtx>();
+    // After passing in a networked game, the player's own submission must not
+    // become the local prompt: hold a lock screen until the next entry arrives.
+    if net.waiting() {
+        return rsx! {
+            div { "Waiting for the next entry…" }
+        };
+    }
+    // In a networked game, until this player has taken their own first turn an
+    // inbound entry must not become their prompt — show the root prompt instead.
+    if net.conn.read().is_some() && !net.rooted() {
+        return rsx! {
+            div {
+                div { "Write something!" }
+                PhraseInput { state }
+            }
+        };
+    }
     match state().last() {
         Some(last) => match last {
             Entry::Phrase(phrase) => rsx! { 
@@ -153,10 +358,19 @@ fn EntryDisplay(state: Signal<Vec<Entry>>) -> Element {
                     ImgSelector { state } 
                 }
             },
-            Entry::Drawing(_v) => rsx! { 
+            Entry::Drawing(_v) => rsx! {
                 div {
-                    DrawingDisplay { 
-                        index: state().len() - 1 
+                    DrawingDisplay {
+                        index: state().len() - 1
+                    }
+                    div { "What is this?" }
+                    PhraseInput { state }
+                }
+            },
+            Entry::Video(_v) => rsx! {
+                div {
+                    VideoDisplay {
+                        index: state().len() - 1
                     }
                     div { "What is this?" }
                     PhraseInput { state }
@@ -172,22 +386,45 @@ fn EntryDisplay(state: Signal<Vec<Entry>>) -> Element {
     }
 }
 
+/// Commit a finished turn. In a networked game the entry is sent to the next
+/// player and this player's input locks (`waiting`) until an inbound `Pass`
+/// arrives — the submission must not become the local `last`/prompt. The first
+/// such submission also marks this player as `rooted`. In a local
+/// pass-the-laptop game the entry is simply pushed onto the stack.
+fn submit_turn(mut net: NetCtx, mut state: Signal<Vec<Entry>>, entry: Entry) {
+    if let Some(ctx) = net.conn.cloned() {
+        let author = std::env::var("TPI_NAME").unwrap_or_else(|_| "me".into());
+        ctx.pass(author, entry);
+        *net.waiting.write() = true;
+        *net.rooted.write() = true;
+    } else {
+        let mut temp = state();
+        temp.push(entry);
+        *state.write() = temp;
+    }
+}
+
 #[component]
 fn ImgSelector(state: Signal<Vec<Entry>>) -> Element {
+    let net = use_context::<NetCtx>();
     rsx! {
         input {
             name: "picture",
             r#type: "file",
-            accept: ".png,.jpg",
+            accept: ".png,.jpg,.mp4,.webm",
             onchange: move |evt| {
                 async move {
                     if let Some(file_engine) = &evt.files() {
-                        let mut temp = state();
                         if file_engine.files().len() > 0 {
-                            temp.push(Entry::Drawing(file_engine.read_file(
-                                file_engine.files()[0].as_str())
-                                    .await.unwrap()));
-                            *state.write() = temp;
+                            let path = file_engine.files()[0].clone();
+                            let bytes = file_engine.read_file(path.as_str()).await.unwrap();
+                            let lower = path.to_lowercase();
+                            let entry = if lower.ends_with(".mp4") || lower.ends_with(".webm") {
+                                Entry::Video(bytes)
+                            } else {
+                                Entry::Drawing(bytes)
+                            };
+                            submit_turn(net, state, entry);
                         }
                     }
                 }
@@ -199,6 +436,7 @@ fn ImgSelector(state: Signal<Vec<Entry>>) -> Element {
 #[component]
 fn PhraseInput(state: Signal<Vec<Entry>>) -> Element {
     let mut small_state = use_signal(|| String::from(""));
+    let net = use_context::<NetCtx>();
     rsx! {
         div {
             textarea {
@@ -213,9 +451,8 @@ fn PhraseInput(state: Signal<Vec<Entry>>) -> Element {
         button {
             width: "80px",
             onclick: move |_evt| {
-                let mut temp = state();
-                temp.push(Entry::Phrase(small_state()));
-                *state.write() = temp;
+                submit_turn(net, state, Entry::Phrase(small_state()));
+                *small_state.write() = String::new();
             },
             "OK"
         }
@@ -239,6 +476,26 @@ fn NewBtn(state: Signal<Vec<Entry>>) -> Element {
     }
 }
 
+/// Magic header written at the start of every new `.tpi` file. Its presence
+/// distinguishes the compressed bincode container from the legacy JSON saves.
+const TPI_MAGIC: &[u8; 4] = b"TPI1";
+
+/// Compression level for the zstd stream. Moderate: image bytes shrink a lot
+/// without a noticeable save-time stall.
+const ZSTD_LEVEL: i32 = 3;
+
+/// Wrap the raw `Entry` stack in `PictionaryEntry` records for on-disk storage.
+/// The author is not tracked in the local game yet, so it is left blank.
+fn to_pictionary(state: &[Entry]) -> Vec<PictionaryEntry> {
+    state
+        .iter()
+        .map(|entry| PictionaryEntry {
+            author: String::new(),
+            entry: entry.clone(),
+        })
+        .collect()
+}
+
 #[component]
 fn SaveBtn(state: Signal<Vec<Entry>>) -> Element {
     let mut small_state = use_signal(|| String::from(""));
@@ -253,12 +510,14 @@ fn SaveBtn(state: Signal<Vec<Entry>>) -> Element {
                         if !filename.ends_with(".tpi") {
                             filename.push_str(".tpi");
                         }
+                        let entries = to_pictionary(&state());
+                        let raw = bincode::serialize(&entries).unwrap();
+                        let packed = zstd::encode_all(raw.as_slice(), ZSTD_LEVEL).unwrap();
                         let mut file = File::create(filename).unwrap();
-                        file.write_all(
-                            serde_json::to_vec(&state()).unwrap().as_slice()
-                        ).unwrap();
+                        file.write_all(TPI_MAGIC).unwrap();
+                        file.write_all(&packed).unwrap();
                     }
-                }, 
+                },
                 "Save"
             }
             "File: "
@@ -287,10 +546,19 @@ fn LoadBtn(state: Signal<Vec<Entry>>) -> Element {
                     async move {
                         if let Some(file_engine) = &evt.files() {
                             if file_engine.files().len() > 0 {
-                                let temp = serde_json::from_str(
-                                    file_engine.read_file_to_string(
-                                        file_engine.files()[0].as_str())
-                                        .await.unwrap().as_str()).unwrap();
+                                let bytes = file_engine.read_file(
+                                    file_engine.files()[0].as_str())
+                                    .await.unwrap();
+                                let temp = if bytes.starts_with(TPI_MAGIC) {
+                                    // New compressed bincode container.
+                                    let raw = zstd::decode_all(&bytes[TPI_MAGIC.len()..]).unwrap();
+                                    let entries: Vec<PictionaryEntry> =
+                                        bincode::deserialize(&raw).unwrap();
+                                    entries.into_iter().map(|pe| pe.entry).collect()
+                                } else {
+                                    // Legacy JSON save: a bare `Vec<Entry>`.
+                                    serde_json::from_slice(&bytes).unwrap()
+                                };
                                 *state.write() = temp;
                             }
                         }
@@ -0,0 +1,119 @@
+//! Networked "pass to the next player" telephone pictionary.
+//!
+//! Each player runs the app on their own machine and connects to a room on a
+//! relay server (see `src/bin/relay.rs`). The server keeps the room in a fixed
+//! turn order and, when a player finishes a turn, forwards *only* the newest
+//! [`PictionaryEntry`] to the next player in line. Nobody ever sees more than
+//! the single previous entry until the game is over, at which point the server
+//! broadcasts the complete ordered stack for the `GameReview` reveal.
+
+use crate::{Entry, PictionaryEntry};
+
+use dioxus::prelude::*;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio_tungstenite::tungstenite::Message;
+
+/// Messages exchanged between a client and the relay.
+///
+/// `Join` announces the seat a client is claiming so the relay can honour the
+/// configured turn order rather than inferring it from connect order. `Pass`
+/// carries the one entry a client is meant to continue from; `Reveal` carries
+/// the assembled stack once every player has taken every turn.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum RoomMessage {
+    /// Announce the room and the seat (turn-order index) this client claims.
+    Join { room: String, player: usize },
+    /// The single previous [`PictionaryEntry`] this player must continue from.
+    Pass(PictionaryEntry),
+    /// The complete ordered stack, broadcast to everyone for the reveal.
+    Reveal(Vec<PictionaryEntry>),
+}
+
+/// Handle to a live room connection.
+///
+/// Modelled on the `RequestContext` pattern: it carries the
+/// outbound half of the socket so the UI can pass its turn on. The room id and
+/// claimed seat are announced to the relay at connect time via [`RoomMessage::Join`].
+pub struct RequestContext {
+    sink: futures_channel::mpsc::UnboundedSender<Message>,
+}
+
+impl RequestContext {
+    /// Send the newest local entry on to the next player in the room.
+    ///
+    /// Only the single `entry` is serialized; the local history never leaves
+    /// this machine.
+    pub fn pass(&self, author: String, entry: Entry) {
+        let msg = RoomMessage::Pass(PictionaryEntry { author, entry });
+        // Use bincode (matching the on-disk `.tpi` format) so image payloads
+        // stay compact instead of ballooning as JSON decimal byte arrays.
+        if let Ok(buf) = bincode::serialize(&msg) {
+            let _ = self.sink.unbounded_send(Message::Binary(buf));
+        }
+    }
+}
+
+/// Connect to `url` for `room`, then pump incoming messages into `state`.
+///
+/// Returns a [`RequestContext`] the UI can use to pass its turn on. A detached
+/// worker task owns the read half of the socket and appends received entries to
+/// the local `state` signal, clearing `waiting` to unlock this player's input.
+/// When the final `Reveal` arrives it replaces `state` with the full ordered
+/// stack.
+pub async fn join_room(
+    url: &str,
+    room: String,
+    player: usize,
+    mut state: Signal<Vec<Entry>>,
+    mut waiting: Signal<bool>,
+) -> RequestContext {
+    let (socket, _resp) = tokio_tungstenite::connect_async(url)
+        .await
+        .expect("failed to connect to relay");
+    let (mut write, mut read) = socket.split();
+
+    let (tx, mut rx) = futures_channel::mpsc::unbounded::<Message>();
+
+    // Announce our claimed seat before anything else so the relay honours the
+    // configured turn order instead of inferring it from connect order.
+    if let Ok(buf) = bincode::serialize(&RoomMessage::Join { room, player }) {
+        let _ = tx.unbounded_send(Message::Binary(buf));
+    }
+
+    // Pump outbound messages from the UI onto the socket.
+    spawn(async move {
+        while let Some(msg) = rx.next().await {
+            if write.send(msg).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    // Pump inbound messages into the local signal.
+    spawn(async move {
+        while let Some(Ok(msg)) = read.next().await {
+            let buf = match msg {
+                Message::Binary(b) => b,
+                Message::Text(t) => t.into_bytes(),
+                _ => continue,
+            };
+            match bincode::deserialize::<RoomMessage>(&buf) {
+                Ok(RoomMessage::Pass(pe)) => {
+                    let mut temp = state();
+                    temp.push(pe.entry);
+                    *state.write() = temp;
+                    // The inbound entry is now the local `last`; unlock input.
+                    *waiting.write() = false;
+                }
+                Ok(RoomMessage::Reveal(stack)) => {
+                    *state.write() = stack.into_iter().map(|pe| pe.entry).collect();
+                    *waiting.write() = false;
+                }
+                Err(_) => continue,
+            }
+        }
+    });
+
+    RequestContext { sink: tx }
+}